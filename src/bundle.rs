@@ -0,0 +1,268 @@
+// `bundle create`/`bundle verify`/`bundle unbundle`: serializing a set of
+// refs (and everything they can reach) to a single transportable file.
+// https://git-scm.com/docs/git-bundle#_bundle_format
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use sha1::{Digest, Sha1};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use crate::pack;
+use crate::{GitObject, GitTree};
+
+/// `bundle create <file> <ref>...`: walks commit -> tree -> blob reachability
+/// from each given ref and writes a header plus a packfile of every object
+/// reached.
+pub fn create(file: &str, refspecs: &[String]) {
+    let mut seen = HashSet::new();
+    let mut objects = Vec::new();
+    let mut tip_lines = Vec::new();
+
+    for refspec in refspecs {
+        let oid = resolve_ref(refspec);
+        collect_reachable(&oid, &mut seen, &mut objects);
+        tip_lines.push(format!("{} {}\n", oid, refspec));
+    }
+
+    let mut out = String::from("# v2 git bundle\n");
+    for line in &tip_lines {
+        out.push_str(line);
+    }
+    out.push('\n');
+
+    let mut out = out.into_bytes();
+    out.extend_from_slice(&build_pack(&objects));
+    fs::write(file, out).unwrap();
+}
+
+/// `bundle verify <file>`: checks that every prerequisite commit (the `-sha
+/// comment` lines used by thin/incremental bundles) already exists locally,
+/// without touching any refs.
+pub fn verify(file: &str) {
+    let data = fs::read(file).unwrap();
+    let header = parse_header(&data).0;
+    let missing = missing_prerequisites(&header.prerequisites);
+    if missing.is_empty() {
+        println!("{} is okay", file);
+    } else {
+        println!("error: {} is missing its prerequisite commits:", file);
+        for oid in &missing {
+            println!("  {}", oid);
+        }
+        std::process::exit(1);
+    }
+}
+
+/// `bundle unbundle <file>`: verifies prerequisites, writes the trailing
+/// packfile under `.git/objects/pack`, and updates the named refs.
+pub fn unbundle(file: &str) {
+    let data = fs::read(file).unwrap();
+    let (header, pack_offset) = parse_header(&data);
+    let missing = missing_prerequisites(&header.prerequisites);
+    if !missing.is_empty() {
+        println!("error: {} is missing its prerequisite commits:", file);
+        for oid in &missing {
+            println!("  {}", oid);
+        }
+        std::process::exit(1);
+    }
+
+    let pack_data = &data[pack_offset..];
+    fs::create_dir_all(".git/objects/pack").unwrap();
+    let pack_name = hex::encode(Sha1::digest(pack_data));
+    fs::write(format!(".git/objects/pack/pack-{}.pack", pack_name), pack_data).unwrap();
+
+    for (oid, refname) in &header.refs {
+        let ref_path = format!(".git/{}", refname);
+        if let Some(parent) = Path::new(&ref_path).parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(&ref_path, format!("{}\n", oid)).unwrap();
+        println!("{} -> {}", refname, oid);
+    }
+}
+
+struct BundleHeader {
+    prerequisites: Vec<String>,
+    refs: Vec<(String, String)>,
+}
+
+/// Parses the `# v2 git bundle` / `# v3 git bundle` signature, any `@`
+/// capability lines (v3 only), `-sha comment` prerequisite lines, and the
+/// `sha refname` tip lines, stopping at the blank line that precedes the
+/// packfile. Returns the header and the offset the packfile starts at.
+fn parse_header(data: &[u8]) -> (BundleHeader, usize) {
+    let (signature, mut index) = read_line(data, 0);
+    assert!(
+        signature == "# v2 git bundle" || signature == "# v3 git bundle",
+        "not a git bundle file (unrecognized signature {:?})",
+        signature
+    );
+
+    let mut prerequisites = Vec::new();
+    let mut refs = Vec::new();
+    loop {
+        let (line, next) = read_line(data, index);
+        index = next;
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with('@') {
+            // v3 capability line, e.g. "@object-format=sha1" — nothing to
+            // act on yet, since this crate only speaks sha1 bundles.
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix('-') {
+            let oid = rest.split(' ').next().unwrap_or_default().to_string();
+            prerequisites.push(oid);
+            continue;
+        }
+        let mut parts = line.splitn(2, ' ');
+        let oid = parts.next().unwrap_or_default().to_string();
+        let refname = parts.next().unwrap_or_default().to_string();
+        refs.push((oid, refname));
+    }
+
+    (BundleHeader { prerequisites, refs }, index)
+}
+
+fn read_line(data: &[u8], start: usize) -> (String, usize) {
+    let end = data[start..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map(|pos| start + pos)
+        .unwrap_or(data.len());
+    (String::from_utf8_lossy(&data[start..end]).to_string(), end + 1)
+}
+
+fn missing_prerequisites(prerequisites: &[String]) -> Vec<String> {
+    prerequisites
+        .iter()
+        .filter(|oid| !object_exists(oid))
+        .cloned()
+        .collect()
+}
+
+fn object_exists(oid: &str) -> bool {
+    let path = format!(".git/objects/{}/{}", &oid[..2], &oid[2..]);
+    Path::new(&path).exists() || pack::read_raw_object_from_packs(oid).is_some()
+}
+
+/// Resolves a ref name (`main`, `refs/heads/main`, `refs/tags/v1`, ...) or a
+/// raw sha to the commit it points at.
+fn resolve_ref(refspec: &str) -> String {
+    for candidate in [
+        format!(".git/{}", refspec),
+        format!(".git/refs/heads/{}", refspec),
+        format!(".git/refs/tags/{}", refspec),
+    ] {
+        if let Ok(contents) = fs::read_to_string(&candidate) {
+            return contents.trim().to_string();
+        }
+    }
+    refspec.to_string()
+}
+
+/// Depth-first walk of commit -> tree -> blob reachability from `oid`,
+/// appending each newly-seen object's `(oid, pack type, body)` to `objects`.
+fn collect_reachable(oid: &str, seen: &mut HashSet<String>, objects: &mut Vec<(String, u8, Vec<u8>)>) {
+    if !seen.insert(oid.to_string()) {
+        return;
+    }
+
+    let framed = crate::read_object_framed(oid);
+    let null_index = framed.iter().position(|&b| b == 0).unwrap();
+    let fmt = &framed[..framed.iter().position(|&b| b == b' ').unwrap()];
+    let body = framed[null_index + 1..].to_vec();
+
+    match fmt {
+        b"commit" => {
+            let (tree, parents) = parse_commit(&body);
+            objects.push((oid.to_string(), pack::OBJ_COMMIT, body));
+            collect_reachable(&tree, seen, objects);
+            for parent in &parents {
+                collect_reachable(parent, seen, objects);
+            }
+        }
+        b"tree" => {
+            let mut tree = GitTree { leaves: Vec::new() };
+            tree.deserialize(&body);
+            objects.push((oid.to_string(), pack::OBJ_TREE, body));
+            for leaf in &tree.leaves {
+                collect_reachable(&leaf.sha_hash, seen, objects);
+            }
+        }
+        b"blob" => {
+            objects.push((oid.to_string(), pack::OBJ_BLOB, body));
+        }
+        other => panic!(
+            "object {} has unsupported type {:?} for a bundle",
+            oid,
+            String::from_utf8_lossy(other)
+        ),
+    }
+}
+
+/// Extracts the `tree` and `parent` lines from a commit object's body.
+fn parse_commit(body: &[u8]) -> (String, Vec<String>) {
+    let text = String::from_utf8_lossy(body);
+    let mut tree = String::new();
+    let mut parents = Vec::new();
+    for line in text.lines() {
+        if line.is_empty() {
+            break;
+        }
+        if let Some(rest) = line.strip_prefix("tree ") {
+            tree = rest.to_string();
+        } else if let Some(rest) = line.strip_prefix("parent ") {
+            parents.push(rest.to_string());
+        }
+    }
+    (tree, parents)
+}
+
+/// Builds a minimal (undeltified) packfile containing exactly `objects`,
+/// each zlib-compressed on its own, followed by the trailing sha1 checksum
+/// every packfile ends with.
+fn build_pack(objects: &[(String, u8, Vec<u8>)]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(b"PACK");
+    out.extend_from_slice(&2u32.to_be_bytes());
+    out.extend_from_slice(&(objects.len() as u32).to_be_bytes());
+
+    for (_oid, obj_type, body) in objects {
+        out.extend_from_slice(&encode_entry_header(*obj_type, body.len()));
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(body).unwrap();
+        out.extend_from_slice(&encoder.finish().unwrap());
+    }
+
+    let checksum = Sha1::digest(&out);
+    out.extend_from_slice(&checksum);
+    out
+}
+
+/// Encodes the pack entry header: type in bits 4-6 of the first byte, size
+/// in the low 4 bits of the first byte plus 7-bit little-endian continuation
+/// bytes (the inverse of `pack::parse_entry_header`).
+fn encode_entry_header(obj_type: u8, size: usize) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut remaining = size >> 4;
+    let mut first = (obj_type << 4) | ((size & 0x0f) as u8);
+    if remaining > 0 {
+        first |= 0x80;
+    }
+    bytes.push(first);
+    while remaining > 0 {
+        let mut byte = (remaining & 0x7f) as u8;
+        remaining >>= 7;
+        if remaining > 0 {
+            byte |= 0x80;
+        }
+        bytes.push(byte);
+    }
+    bytes
+}
@@ -0,0 +1,84 @@
+// Minimal `.git/config` reader, just enough to notice the object-format
+// extension git uses to flag a sha256 repository.
+
+use std::fs;
+
+#[derive(Clone, Copy)]
+pub enum ObjectFormat {
+    Sha1,
+    Sha256,
+}
+
+impl ObjectFormat {
+    pub fn hash_len(self) -> usize {
+        match self {
+            ObjectFormat::Sha1 => 20,
+            ObjectFormat::Sha256 => 32,
+        }
+    }
+}
+
+/// Reads `extensions.objectFormat` out of `.git/config`, defaulting to
+/// `Sha1` when the repo doesn't have one (every repo created before git
+/// 2.29, and every sha1 repo since).
+pub fn object_format() -> ObjectFormat {
+    let contents = match fs::read_to_string(".git/config") {
+        Ok(contents) => contents,
+        Err(_) => return ObjectFormat::Sha1,
+    };
+
+    let mut in_extensions_section = false;
+    for line in contents.lines() {
+        let line = line.trim();
+        if let Some(section) = line.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            in_extensions_section = section.eq_ignore_ascii_case("extensions");
+            continue;
+        }
+        if !in_extensions_section {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            if key.trim().eq_ignore_ascii_case("objectFormat") && value.trim() == "sha256" {
+                return ObjectFormat::Sha256;
+            }
+        }
+    }
+    ObjectFormat::Sha1
+}
+
+/// Reads `user.name`/`user.email` from `.git/config`, falling back to a
+/// generic identity when the repo (or this tool) never set them.
+pub fn user_identity() -> (String, String) {
+    let contents = match fs::read_to_string(".git/config") {
+        Ok(contents) => contents,
+        Err(_) => return default_identity(),
+    };
+
+    let mut in_user_section = false;
+    let mut name = None;
+    let mut email = None;
+    for line in contents.lines() {
+        let line = line.trim();
+        if let Some(section) = line.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            in_user_section = section.eq_ignore_ascii_case("user");
+            continue;
+        }
+        if !in_user_section {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            match key.trim().to_ascii_lowercase().as_str() {
+                "name" => name = Some(value.trim().to_string()),
+                "email" => email = Some(value.trim().to_string()),
+                _ => {}
+            }
+        }
+    }
+
+    let (default_name, default_email) = default_identity();
+    (name.unwrap_or(default_name), email.unwrap_or(default_email))
+}
+
+fn default_identity() -> (String, String) {
+    ("codecrafters-git-rust".to_string(), "author@example.com".to_string())
+}
@@ -1,13 +1,22 @@
 use flate2::read::ZlibDecoder;
 use flate2::write::ZlibEncoder;
 use flate2::Compression;
-use sha1::{Digest, Sha1};
+use sha1::{Digest as _, Sha1};
+use sha2::Sha256;
 
 #[allow(unused_imports)]
 use std::env;
 #[allow(unused_imports)]
 use std::fs;
 use std::io::prelude::*;
+use std::path::Path;
+
+use config::ObjectFormat;
+
+mod bundle;
+mod config;
+mod pack;
+mod transport;
 
 pub trait GitObject {
     // Method to serialize the object. This must be implemented by any struct implementing the trait.
@@ -40,6 +49,7 @@ impl GitObject for GitBlob {
 enum GitObjectType {
     Blob(GitBlob),
     Tree(GitTree),
+    Commit(GitCommit),
 }
 
 #[derive(Clone)]
@@ -50,7 +60,11 @@ pub struct GitTreeLeaf {
     pub sha_hash: String,
 }
 
-fn tree_parse_one(raw_bytes: &[u8], start_index: usize) -> (GitTreeLeaf, usize) {
+fn tree_parse_one(
+    raw_bytes: &[u8],
+    start_index: usize,
+    object_format: ObjectFormat,
+) -> (GitTreeLeaf, usize) {
     let mut index = start_index;
     let mut mode = [0; 6];
     while raw_bytes[index] != b' ' {
@@ -73,8 +87,8 @@ fn tree_parse_one(raw_bytes: &[u8], start_index: usize) -> (GitTreeLeaf, usize)
     }
     index += 1;
     let mut sha_hash = String::new();
-    // the sha1 hash is 20 bytes long and in big endian format
-    for _ in 0..20 {
+    // the hash is in big endian format, 20 bytes for sha1 or 32 for sha256
+    for _ in 0..object_format.hash_len() {
         sha_hash.push_str(&format!("{:02x}", raw_bytes[index]));
         index += 1;
     }
@@ -89,10 +103,12 @@ fn tree_parse_one(raw_bytes: &[u8], start_index: usize) -> (GitTreeLeaf, usize)
 }
 
 fn tree_parse(raw_bytes: &[u8]) -> Vec<GitTreeLeaf> {
+    // resolved once per tree, not once per leaf, since it re-reads `.git/config`
+    let object_format = config::object_format();
     let mut index = 0;
     let mut result = Vec::new();
     while index < raw_bytes.len() {
-        let (leaf, new_index) = tree_parse_one(raw_bytes, index);
+        let (leaf, new_index) = tree_parse_one(raw_bytes, index, object_format);
         result.push(leaf);
         index = new_index;
     }
@@ -100,11 +116,11 @@ fn tree_parse(raw_bytes: &[u8]) -> Vec<GitTreeLeaf> {
 }
 
 fn tree_leaf_sort_key(leaf: &GitTreeLeaf) -> String {
-    if leaf.mode.starts_with(b"10") {
-        leaf.path.clone()
+    if trimmed_mode(&leaf.mode) == "40000" {
+        // real trees are sorted as if their name had a trailing slash
+        format!("{}/", leaf.path)
     } else {
-        // directories are sorted with a trailing slash
-        format!("{}\\", leaf.path)
+        leaf.path.clone()
     }
 }
 
@@ -144,6 +160,53 @@ impl GitObject for GitTree {
     }
 }
 
+pub struct GitCommit {
+    pub tree: String,
+    pub parents: Vec<String>,
+    pub author: String,
+    pub committer: String,
+    pub message: String,
+}
+
+impl GitObject for GitCommit {
+    fn fmt(&self) -> &[u8] {
+        b"commit"
+    }
+
+    fn serialize(&self) -> Vec<u8> {
+        let mut result = Vec::new();
+        result.extend_from_slice(format!("tree {}\n", self.tree).as_bytes());
+        for parent in &self.parents {
+            result.extend_from_slice(format!("parent {}\n", parent).as_bytes());
+        }
+        result.extend_from_slice(format!("author {}\n", self.author).as_bytes());
+        result.extend_from_slice(format!("committer {}\n", self.committer).as_bytes());
+        result.push(b'\n');
+        result.extend_from_slice(self.message.trim_end_matches('\n').as_bytes());
+        result.push(b'\n');
+        result
+    }
+
+    fn deserialize(&mut self, data: &[u8]) {
+        let text = String::from_utf8_lossy(data);
+        let mut sections = text.splitn(2, "\n\n");
+        let header = sections.next().unwrap_or_default();
+        self.message = sections.next().unwrap_or_default().to_string();
+
+        for line in header.lines() {
+            if let Some(rest) = line.strip_prefix("tree ") {
+                self.tree = rest.to_string();
+            } else if let Some(rest) = line.strip_prefix("parent ") {
+                self.parents.push(rest.to_string());
+            } else if let Some(rest) = line.strip_prefix("author ") {
+                self.author = rest.to_string();
+            } else if let Some(rest) = line.strip_prefix("committer ") {
+                self.committer = rest.to_string();
+            }
+        }
+    }
+}
+
 fn main() {
     let args: Vec<String> = env::args().collect();
     match args[1].as_str() {
@@ -162,8 +225,12 @@ fn main() {
                     std::io::stdout().write_all(&blob.serialize()).unwrap();
                     std::io::stdout().flush().unwrap();
                 }
+                GitObjectType::Commit(commit) => {
+                    std::io::stdout().write_all(&commit.serialize()).unwrap();
+                    std::io::stdout().flush().unwrap();
+                }
                 _ => {
-                    println!("unexpected object type for cat-file"); 
+                    println!("unexpected object type for cat-file");
                 }
             }
         }
@@ -176,12 +243,36 @@ fn main() {
         }
         "ls-tree" => {
             let hash = &args[args.len() - 1];
+            let options = LsTreeOptions::parse(&args[2..args.len() - 1]);
             let object = read_object(hash);
             match object {
-                GitObjectType::Tree(tree) => ls_tree(tree),
+                GitObjectType::Tree(tree) => ls_tree(tree, &options, ""),
                 _ => println!("not a tree object"),
             }
         }
+        "write-tree" => {
+            let hash = write_tree(Path::new("."));
+            println!("{}", hash);
+        }
+        "commit-tree" => {
+            let hash = commit_tree(&args[2..]);
+            println!("{}", hash);
+        }
+        "clone" => {
+            let url = &args[2];
+            let dir = &args[3];
+            transport::clone(url, dir);
+        }
+        "bundle" => {
+            let subcommand = args[2].as_str();
+            let file = &args[3];
+            match subcommand {
+                "create" => bundle::create(file, &args[4..]),
+                "verify" => bundle::verify(file),
+                "unbundle" => bundle::unbundle(file),
+                _ => println!("unknown bundle subcommand: {}", subcommand),
+            }
+        }
         _ => {
             println!("unknown command: {}", args[1])
         }
@@ -190,6 +281,10 @@ fn main() {
 
 fn read_object(hash: &str) -> GitObjectType {
     let path = format!(".git/objects/{}/{}", &hash[..2], &hash[2..]);
+    if !Path::new(&path).exists() {
+        return pack::read_object_from_packs(hash)
+            .unwrap_or_else(|| panic!("object {} not found in loose storage or any pack", hash));
+    }
     let data = fs::read(path).unwrap();
     let mut decoder = ZlibDecoder::new(data.as_slice());
     let mut decoded_bytes = Vec::new();
@@ -210,12 +305,48 @@ fn read_object(hash: &str) -> GitObjectType {
             tree.deserialize(byte_contents);
             GitObjectType::Tree(tree)
         },
+        b"commit" => {
+            let mut commit = GitCommit {
+                tree: String::new(),
+                parents: Vec::new(),
+                author: String::new(),
+                committer: String::new(),
+                message: String::new(),
+            };
+            commit.deserialize(byte_contents);
+            GitObjectType::Commit(commit)
+        },
         _ => panic!("unknown object type"),
     }
 }
 
+/// Reads an object's full `"<type> <len>\0<body>"` framing regardless of
+/// whether `GitObjectType` models that type yet (e.g. commits), checking
+/// loose storage before falling back to the packs. Used by callers like
+/// `clone` and `bundle` that only need to peek at an object's bytes.
+pub(crate) fn read_object_framed(hash: &str) -> Vec<u8> {
+    let path = format!(".git/objects/{}/{}", &hash[..2], &hash[2..]);
+    if Path::new(&path).exists() {
+        let compressed = fs::read(path).unwrap();
+        let mut decoder = ZlibDecoder::new(compressed.as_slice());
+        let mut decoded = Vec::new();
+        decoder.read_to_end(&mut decoded).unwrap();
+        decoded
+    } else {
+        let (obj_type, data) = pack::read_raw_object_from_packs(hash)
+            .unwrap_or_else(|| panic!("object {} not found in loose storage or any pack", hash));
+        let mut framed = pack::type_name(obj_type).to_vec();
+        framed.push(b' ');
+        framed.extend_from_slice(data.len().to_string().as_bytes());
+        framed.push(0);
+        framed.extend_from_slice(&data);
+        framed
+    }
+}
+
 fn write_object(object: impl GitObject) -> String {
-    // returns the sha1 hash of the object
+    // returns the object id of the object, hashed with whichever format
+    // `.git/config` selects (sha1 unless `extensions.objectFormat = sha256`)
     let serialized = object.serialize();
     let mut result = Vec::new();
     result.extend_from_slice(object.fmt());
@@ -223,9 +354,18 @@ fn write_object(object: impl GitObject) -> String {
     result.extend_from_slice(serialized.len().to_string().as_bytes());
     result.push(b'\0');
     result.extend_from_slice(&serialized);
-    let mut hasher = Sha1::new();
-    hasher.update(&result);
-    let hash_result = hasher.finalize();
+    let hash_result: Vec<u8> = match config::object_format() {
+        ObjectFormat::Sha1 => {
+            let mut hasher = Sha1::new();
+            hasher.update(&result);
+            hasher.finalize().to_vec()
+        }
+        ObjectFormat::Sha256 => {
+            let mut hasher = Sha256::new();
+            hasher.update(&result);
+            hasher.finalize().to_vec()
+        }
+    };
     let sha_string = hex::encode(hash_result);
     let path = format!(".git/objects/{}/{}", &sha_string[..2], &sha_string[2..]);
     fs::create_dir_all(format!(".git/objects/{}", &sha_string[..2])).unwrap();
@@ -236,8 +376,164 @@ fn write_object(object: impl GitObject) -> String {
     sha_string
 }
 
-fn ls_tree(tree: GitTree) {
+#[derive(Default)]
+struct LsTreeOptions {
+    name_only: bool,
+    recursive: bool,
+    long: bool,
+    show_trees: bool,
+}
+
+impl LsTreeOptions {
+    fn parse(flags: &[String]) -> LsTreeOptions {
+        let mut options = LsTreeOptions::default();
+        for flag in flags {
+            match flag.as_str() {
+                "--name-only" => options.name_only = true,
+                "-r" => options.recursive = true,
+                "-l" | "--long" => options.long = true,
+                "-t" => options.show_trees = true,
+                _ => {}
+            }
+        }
+        options
+    }
+}
+
+/// Mirrors `git ls-tree`: `<mode> <type> <sha>\t<path>`, with `--name-only`,
+/// `-r` (recurse, emitting nested paths), `-l`/`--long` (add the blob's byte
+/// size), and `-t` (keep printing tree entries while recursing).
+fn ls_tree(tree: GitTree, options: &LsTreeOptions, prefix: &str) {
     for leaf in tree.leaves {
-        println!("{}", leaf.path);
+        let full_path = if prefix.is_empty() {
+            leaf.path.clone()
+        } else {
+            format!("{}/{}", prefix, leaf.path)
+        };
+        let mode = trimmed_mode(&leaf.mode);
+        let entry_type = entry_type_for_mode(&mode);
+        let is_tree = entry_type == "tree";
+
+        let should_print = !(is_tree && options.recursive && !options.show_trees);
+        if should_print {
+            if options.name_only {
+                println!("{}", full_path);
+            } else if options.long {
+                let size = blob_size(entry_type, &leaf.sha_hash);
+                println!("{:0>6} {} {} {:>7}\t{}", mode, entry_type, leaf.sha_hash, size, full_path);
+            } else {
+                println!("{:0>6} {} {}\t{}", mode, entry_type, leaf.sha_hash, full_path);
+            }
+        }
+
+        if is_tree && options.recursive {
+            if let GitObjectType::Tree(child) = read_object(&leaf.sha_hash) {
+                ls_tree(child, options, &full_path);
+            }
+        }
+    }
+}
+
+/// `leaf.mode` is a fixed 6-byte array and shorter modes (e.g. `40000` for a
+/// tree) are left null-padded at the end, so trim those before using it.
+fn trimmed_mode(mode: &[u8]) -> String {
+    mode.iter()
+        .take_while(|&&b| b != 0)
+        .map(|&b| b as char)
+        .collect()
+}
+
+fn entry_type_for_mode(mode: &str) -> &'static str {
+    match mode {
+        "40000" => "tree",
+        "160000" => "commit",
+        _ => "blob",
+    }
+}
+
+fn blob_size(entry_type: &str, sha_hash: &str) -> String {
+    if entry_type != "blob" {
+        return "-".to_string();
+    }
+    match read_object(sha_hash) {
+        GitObjectType::Blob(blob) => blob.blob_data.len().to_string(),
+        _ => "-".to_string(),
+    }
+}
+
+/// Recursively walks `dir` (skipping `.git`), hashing each file as a blob
+/// and each subdirectory as a nested tree, and returns the root tree's hash.
+fn write_tree(dir: &Path) -> String {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut entries: Vec<fs::DirEntry> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap())
+        .filter(|entry| entry.file_name() != ".git")
+        .collect();
+    entries.sort_by_key(|entry| entry.file_name());
+
+    let mut leaves = Vec::new();
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        let metadata = fs::symlink_metadata(&path).unwrap();
+
+        let (mode, sha_hash): (&[u8], String) = if metadata.is_dir() {
+            (b"40000", write_tree(&path))
+        } else if metadata.file_type().is_symlink() {
+            let target = fs::read_link(&path).unwrap();
+            let blob = GitBlob { blob_data: target.to_string_lossy().into_owned().into_bytes() };
+            (b"120000", write_object(blob))
+        } else if metadata.permissions().mode() & 0o111 != 0 {
+            let blob = GitBlob { blob_data: fs::read(&path).unwrap() };
+            (b"100755", write_object(blob))
+        } else {
+            let blob = GitBlob { blob_data: fs::read(&path).unwrap() };
+            (b"100644", write_object(blob))
+        };
+
+        leaves.push(GitTreeLeaf { mode: mode.to_vec(), path: name, sha_hash });
     }
+
+    write_object(GitTree { leaves })
+}
+
+/// Builds and writes a commit object from `-p <parent>` (repeatable) and
+/// `-m <msg>` arguments following the tree hash.
+fn commit_tree(args: &[String]) -> String {
+    let tree = args[0].clone();
+    let mut parents = Vec::new();
+    let mut message = String::new();
+
+    let mut index = 1;
+    while index < args.len() {
+        match args[index].as_str() {
+            "-p" => {
+                parents.push(args[index + 1].clone());
+                index += 2;
+            }
+            "-m" => {
+                message = args[index + 1].clone();
+                index += 2;
+            }
+            _ => index += 1,
+        }
+    }
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let (name, email) = config::user_identity();
+    // timezone is hardcoded to UTC since this crate doesn't read the local offset
+    let identity = format!("{} <{}> {} +0000", name, email, timestamp);
+
+    write_object(GitCommit {
+        tree,
+        parents,
+        author: identity.clone(),
+        committer: identity,
+        message,
+    })
 }
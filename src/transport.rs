@@ -0,0 +1,294 @@
+// `clone` support: a pkt-line codec plus the smart HTTP transport's
+// upload-pack v2 conversation (https://git-scm.com/docs/protocol-v2).
+
+use sha1::{Digest, Sha1};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use crate::GitObjectType;
+
+const FLUSH_PKT: &[u8] = b"0000";
+const DELIM_PKT: &[u8] = b"0001";
+
+enum PktLine {
+    Data(Vec<u8>),
+    Flush,
+    Delim,
+}
+
+/// Encodes `payload` as a single pkt-line: a 4-byte lowercase-hex length
+/// (covering the prefix itself) followed by the payload bytes.
+fn encode_pkt_line(payload: &str) -> Vec<u8> {
+    let len = payload.len() + 4;
+    let mut line = format!("{:04x}", len).into_bytes();
+    line.extend_from_slice(payload.as_bytes());
+    line
+}
+
+fn encode_pkt_lines(lines: &[&str]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for line in lines {
+        out.extend_from_slice(&encode_pkt_line(line));
+    }
+    out
+}
+
+/// Splits a raw pkt-line stream into its constituent lines.
+fn decode_pkt_lines(data: &[u8]) -> Vec<PktLine> {
+    let mut lines = Vec::new();
+    let mut index = 0;
+    while index + 4 <= data.len() {
+        let len_hex = std::str::from_utf8(&data[index..index + 4]).unwrap();
+        let len = usize::from_str_radix(len_hex, 16).expect("invalid pkt-line length");
+        match len {
+            0 => {
+                lines.push(PktLine::Flush);
+                index += 4;
+            }
+            1 => {
+                lines.push(PktLine::Delim);
+                index += 4;
+            }
+            _ => {
+                lines.push(PktLine::Data(data[index + 4..index + len].to_vec()));
+                index += len;
+            }
+        }
+    }
+    lines
+}
+
+struct RemoteRef {
+    oid: String,
+    name: String,
+    symref_target: Option<String>,
+}
+
+fn post(url: &str, service: &str, body: Vec<u8>) -> Vec<u8> {
+    let endpoint = format!("{}/{}", url.trim_end_matches('/'), service);
+    let response = ureq::post(&endpoint)
+        .set("Content-Type", &format!("application/x-{}-request", service))
+        .set("Git-Protocol", "version=2")
+        .send_bytes(&body)
+        .unwrap_or_else(|err| panic!("request to {} failed: {}", endpoint, err));
+    let mut out = Vec::new();
+    response
+        .into_reader()
+        .read_to_end(&mut out)
+        .expect("failed to read response body");
+    out
+}
+
+/// `GET $url/info/refs?service=git-upload-pack` with `Git-Protocol:
+/// version=2`, returning the advertised capability lines.
+fn discover_capabilities(url: &str) -> Vec<String> {
+    let endpoint = format!(
+        "{}/info/refs?service=git-upload-pack",
+        url.trim_end_matches('/')
+    );
+    let response = ureq::get(&endpoint)
+        .set("Git-Protocol", "version=2")
+        .call()
+        .unwrap_or_else(|err| panic!("request to {} failed: {}", endpoint, err));
+    let mut body = Vec::new();
+    response
+        .into_reader()
+        .read_to_end(&mut body)
+        .expect("failed to read response body");
+
+    decode_pkt_lines(&body)
+        .into_iter()
+        .filter_map(|line| match line {
+            PktLine::Data(payload) => Some(String::from_utf8_lossy(&payload).trim_end().to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// `command=ls-refs` with `symrefs` and a `refs/heads/` prefix, used to
+/// discover branch tips (and which one HEAD points at) before fetching.
+fn ls_refs(url: &str) -> Vec<RemoteRef> {
+    let mut request = encode_pkt_lines(&["command=ls-refs\n", "agent=git/codecrafters-git-rust\n"]);
+    request.extend_from_slice(DELIM_PKT);
+    request.extend_from_slice(&encode_pkt_lines(&[
+        "symrefs\n",
+        "peel\n",
+        "ref-prefix HEAD\n",
+        "ref-prefix refs/heads/\n",
+    ]));
+    request.extend_from_slice(FLUSH_PKT);
+
+    let response = post(url, "git-upload-pack", request);
+    decode_pkt_lines(&response)
+        .into_iter()
+        .filter_map(|line| match line {
+            PktLine::Data(payload) => Some(parse_ls_refs_line(&payload)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn parse_ls_refs_line(payload: &[u8]) -> RemoteRef {
+    let line = String::from_utf8_lossy(payload).trim_end().to_string();
+    let mut parts = line.splitn(2, ' ');
+    let oid = parts.next().unwrap().to_string();
+    let rest = parts.next().unwrap_or_default();
+    let mut fields = rest.split(' ');
+    let name = fields.next().unwrap_or_default().to_string();
+    let symref_target = fields
+        .find_map(|field| field.strip_prefix("symref-target:"))
+        .map(|target| target.to_string());
+    RemoteRef {
+        oid,
+        name,
+        symref_target,
+    }
+}
+
+/// `command=fetch` with one `want` line per requested tip, demultiplexing
+/// the sideband response into a raw packfile.
+fn fetch_pack(url: &str, wants: &[String]) -> Vec<u8> {
+    let mut request = encode_pkt_lines(&["command=fetch\n", "agent=git/codecrafters-git-rust\n"]);
+    request.extend_from_slice(DELIM_PKT);
+    let want_lines: Vec<String> = wants.iter().map(|oid| format!("want {}\n", oid)).collect();
+    let arg_lines: Vec<&str> = want_lines
+        .iter()
+        .map(|line| line.as_str())
+        .chain(["no-progress\n", "done\n"])
+        .collect();
+    request.extend_from_slice(&encode_pkt_lines(&arg_lines));
+    request.extend_from_slice(FLUSH_PKT);
+
+    let response = post(url, "git-upload-pack", request);
+    demux_packfile_section(&decode_pkt_lines(&response))
+}
+
+/// Finds the `packfile` section and concatenates every sideband-1 chunk,
+/// ignoring progress (band 2) and failing loudly on a fatal error (band 3).
+fn demux_packfile_section(lines: &[PktLine]) -> Vec<u8> {
+    let mut packfile = Vec::new();
+    let mut in_packfile_section = false;
+    for line in lines {
+        match line {
+            PktLine::Data(payload) => {
+                if !in_packfile_section {
+                    if payload.as_slice() == b"packfile\n" {
+                        in_packfile_section = true;
+                    }
+                    continue;
+                }
+                match payload[0] {
+                    1 => packfile.extend_from_slice(&payload[1..]),
+                    2 => {}
+                    3 => panic!(
+                        "remote reported a fatal error: {}",
+                        String::from_utf8_lossy(&payload[1..])
+                    ),
+                    other => panic!("unknown sideband channel {}", other),
+                }
+            }
+            PktLine::Delim | PktLine::Flush => in_packfile_section = false,
+        }
+    }
+    packfile
+}
+
+/// Clones `url` into a freshly created `dir`: runs the v2 `ls-refs`/`fetch`
+/// conversation, writes the resulting pack and refs, then checks out HEAD.
+pub fn clone(url: &str, dir: &str) {
+    let target_dir = Path::new(dir);
+    fs::create_dir_all(target_dir).unwrap();
+    let git_dir = target_dir.join(".git");
+    fs::create_dir_all(git_dir.join("objects").join("pack")).unwrap();
+    fs::create_dir_all(git_dir.join("refs").join("heads")).unwrap();
+
+    let capabilities = discover_capabilities(url);
+    assert!(
+        capabilities.iter().any(|line| line.contains("version 2")),
+        "remote {} does not advertise protocol version 2",
+        url
+    );
+
+    let refs = ls_refs(url);
+    let head_ref = refs.iter().find(|r| r.name == "HEAD");
+    let head_target = head_ref
+        .and_then(|r| r.symref_target.clone())
+        .unwrap_or_else(|| {
+            refs.iter()
+                .find(|r| r.name.starts_with("refs/heads/"))
+                .expect("remote repository has no branches")
+                .name
+                .clone()
+        });
+
+    let branch_refs: Vec<&RemoteRef> = refs.iter().filter(|r| r.name != "HEAD").collect();
+    let wants: Vec<String> = branch_refs.iter().map(|r| r.oid.clone()).collect();
+    let pack_data = fetch_pack(url, &wants);
+
+    let pack_name = hex::encode(Sha1::digest(&pack_data));
+    let pack_path = git_dir
+        .join("objects")
+        .join("pack")
+        .join(format!("pack-{}.pack", pack_name));
+    fs::write(&pack_path, &pack_data).unwrap();
+
+    for r in &branch_refs {
+        let ref_path = git_dir.join(&r.name);
+        fs::create_dir_all(ref_path.parent().unwrap()).unwrap();
+        fs::write(ref_path, format!("{}\n", r.oid)).unwrap();
+    }
+    fs::write(git_dir.join("HEAD"), format!("ref: {}\n", head_target)).unwrap();
+
+    let head_oid = branch_refs
+        .iter()
+        .find(|r| r.name == head_target)
+        .map(|r| r.oid.clone())
+        .expect("HEAD target was not among the fetched refs");
+    checkout_commit(target_dir, &head_oid);
+}
+
+/// Materializes the tree pointed at by commit `oid` into `dest`, reusing
+/// `read_object`/`GitTree` the same way `ls-tree` does.
+///
+/// There's no typed commit object yet (`GitObjectType` only knows about
+/// blobs and trees), so the tree line is picked out of the raw commit body
+/// by hand instead of going through `read_object`.
+fn checkout_commit(dest: &Path, oid: &str) {
+    let tree_oid = commit_tree_oid(oid);
+    checkout_tree(dest, &tree_oid);
+}
+
+fn commit_tree_oid(oid: &str) -> String {
+    let framed = crate::read_object_framed(oid);
+    let null_index = framed.iter().position(|&b| b == 0).unwrap();
+    let body = &framed[null_index + 1..];
+    let first_line = body
+        .split(|&b| b == b'\n')
+        .next()
+        .expect("commit object has no content");
+    String::from_utf8_lossy(first_line)
+        .strip_prefix("tree ")
+        .unwrap_or_else(|| panic!("commit {} does not start with a tree line", oid))
+        .to_string()
+}
+
+fn checkout_tree(dest: &Path, tree_oid: &str) {
+    let tree = match crate::read_object(tree_oid) {
+        GitObjectType::Tree(tree) => tree,
+        _ => panic!("expected a tree object while checking out {}", tree_oid),
+    };
+    for leaf in &tree.leaves {
+        let path = dest.join(&leaf.path);
+        if leaf.mode.starts_with(b"40") {
+            fs::create_dir_all(&path).unwrap();
+            checkout_tree(&path, &leaf.sha_hash);
+        } else {
+            let blob = match crate::read_object(&leaf.sha_hash) {
+                GitObjectType::Blob(blob) => blob,
+                _ => panic!("expected a blob object while checking out {}", leaf.sha_hash),
+            };
+            fs::write(&path, &blob.blob_data).unwrap();
+        }
+    }
+}